@@ -3,7 +3,7 @@ use std::io::{self, prelude::*};
 use std::iter;
 
 /// A text color
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Color {
     /// Standard 4-bit color in range 0..8
     Standard(u8),
@@ -23,6 +23,49 @@ pub enum Color {
     True(u8, u8, u8),
 }
 
+/// The richest kind of color a terminal can render.
+///
+/// Detected once from the environment at [`Console::new`] and used to
+/// down-convert any [`Color`] to the highest tier the terminal understands, so
+/// a truecolor escape is never sent to a 16-color terminal where it would
+/// render wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorLevel {
+    /// The 16 standard/bold palette entries only.
+    Ansi16,
+    /// The 256-color indexed palette (cube + grayscale ramp).
+    Indexed256,
+    /// Full 24-bit color.
+    TrueColor,
+}
+
+impl ColorLevel {
+    /// Detect the color capability from `COLORTERM`/`TERM`.
+    pub fn detect() -> Self {
+        match std::env::var("COLORTERM") {
+            Ok(v) if v == "truecolor" || v == "24bit" => return Self::TrueColor,
+            _ => {}
+        }
+        match std::env::var("TERM") {
+            Ok(term) if term.contains("256color") => Self::Indexed256,
+            _ => Self::Ansi16,
+        }
+    }
+}
+
+impl std::str::FromStr for ColorLevel {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "16" | "ansi" | "ansi16" => Ok(Self::Ansi16),
+            "256" | "indexed" | "indexed256" => Ok(Self::Indexed256),
+            "truecolor" | "24bit" | "true" => Ok(Self::TrueColor),
+            _ => Err("expected 16, 256, or truecolor"),
+        }
+    }
+}
+
 /// A text style
 #[derive(Debug, Clone)]
 pub enum Style {
@@ -33,14 +76,236 @@ pub enum Style {
     Compound(Vec<Style>),
 }
 
+/// A decoded key press.
+///
+/// `read_one_char` hands back raw bytes, which is enough to echo printable
+/// characters but not enough to drive navigation; [`Console::read_key`] folds
+/// the multi-byte CSI/SS3 escape sequences a terminal emits for the arrow and
+/// editing keys into these variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Delete,
+    Backspace,
+    Enter,
+    Esc,
+    Tab,
+    CtrlC,
+    /// A `Ctrl`-modified letter other than the ones with a dedicated variant
+    /// (e.g. `Ctrl('a')`), normalized to lowercase.
+    Ctrl(char),
+    /// A byte or sequence we don't have a name for.
+    Unknown(u8),
+}
+
+#[cfg(not(windows))]
 pub struct Console {
-    #[cfg(not(windows))]
     prev_termios: libc::termios,
+
+    /// Whether to emit the synchronized-update private-mode sequences. Terminals
+    /// that don't understand them ignore them harmlessly; `new` turns this on and
+    /// nothing turns it off.
+    sync_updates: bool,
+
+    /// The richest color tier this terminal can render; colors are degraded to
+    /// it before emission.
+    color_level: ColorLevel,
+}
+
+/// The 16 standard/bold palette entries, in xterm's default RGB values.
+const PALETTE_16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Standard(0), (0, 0, 0)),
+    (Color::Standard(1), (170, 0, 0)),
+    (Color::Standard(2), (0, 170, 0)),
+    (Color::Standard(3), (170, 85, 0)),
+    (Color::Standard(4), (0, 0, 170)),
+    (Color::Standard(5), (170, 0, 170)),
+    (Color::Standard(6), (0, 170, 170)),
+    (Color::Standard(7), (170, 170, 170)),
+    (Color::Bold(0), (85, 85, 85)),
+    (Color::Bold(1), (255, 85, 85)),
+    (Color::Bold(2), (85, 255, 85)),
+    (Color::Bold(3), (255, 255, 85)),
+    (Color::Bold(4), (85, 85, 255)),
+    (Color::Bold(5), (255, 85, 255)),
+    (Color::Bold(6), (85, 255, 255)),
+    (Color::Bold(7), (255, 255, 255)),
+];
+
+impl Color {
+    /// Parse a color from X11 `XParseColor` notation into a [`Color::True`].
+    ///
+    /// Two spellings are accepted: a leading `#` followed by a hex string whose
+    /// length is divisible by three (giving `len / 3` hex digits per channel,
+    /// up to four), and the `rgb:` prefix followed by three slash-separated hex
+    /// groups which may each be a different width. Every channel is scaled to 8
+    /// bits as `255 * value / (16^digits - 1)`, so `rgb:f/f/f` and `#ffffff`
+    /// both yield `255`. Malformed input returns `None`.
+    pub fn parse(s: &str) -> Option<Self> {
+        /// Parse a hex group and scale the result to the 0..=255 range.
+        fn channel(group: &str) -> Option<u8> {
+            if group.is_empty() {
+                return None;
+            }
+            let value = u32::from_str_radix(group, 16).ok()?;
+            let max = 16u32.checked_pow(group.len() as u32)? - 1;
+            Some((255 * value / max) as u8)
+        }
+
+        if let Some(hex) = s.strip_prefix('#') {
+            if hex.is_empty() || hex.len() % 3 != 0 {
+                return None;
+            }
+            let width = hex.len() / 3;
+            if !(1..=4).contains(&width) {
+                return None;
+            }
+            let r = channel(&hex[..width])?;
+            let g = channel(&hex[width..2 * width])?;
+            let b = channel(&hex[2 * width..])?;
+            return Some(Color::True(r, g, b));
+        }
+
+        if let Some(groups) = s.strip_prefix("rgb:") {
+            let mut parts = groups.split('/');
+            let r = channel(parts.next()?)?;
+            let g = channel(parts.next()?)?;
+            let b = channel(parts.next()?)?;
+            if parts.next().is_some() {
+                return None;
+            }
+            return Some(Color::True(r, g, b));
+        }
+
+        None
+    }
+
+    /// The approximate 24-bit RGB value this color resolves to, used as the
+    /// common ground when degrading between tiers.
+    fn to_rgb(self) -> (u8, u8, u8) {
+        /// The six intensity steps a cube component maps onto.
+        const CUBE_STEPS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+        match self {
+            Color::Standard(n) => PALETTE_16[n as usize].1,
+            Color::Bold(n) => PALETTE_16[n as usize + 8].1,
+            Color::Cube(r, g, b) => (
+                CUBE_STEPS[r as usize],
+                CUBE_STEPS[g as usize],
+                CUBE_STEPS[b as usize],
+            ),
+            Color::Grayscale(n) => {
+                let v = 8 + 10 * n;
+                (v, v, v)
+            }
+            Color::True(r, g, b) => (r, g, b),
+        }
+    }
+
+    /// Map a truecolor value onto the 256-color indexed palette.
+    fn to_indexed256(self) -> Color {
+        match self {
+            Color::True(r, g, b) => {
+                if r == g && g == b {
+                    let luma = r as i32;
+                    let step = ((luma - 8) / 10).clamp(0, 23);
+                    Color::Grayscale(step as u8)
+                } else {
+                    let q = |c: u8| ((c as f32 / 255.0 * 5.0).round()) as u8;
+                    Color::Cube(q(r), q(g), q(b))
+                }
+            }
+            other => other,
+        }
+    }
+
+    /// Pick the nearest of the 16 palette entries by squared-RGB distance.
+    fn to_ansi16(self) -> Color {
+        let (r, g, b) = self.to_rgb();
+        PALETTE_16
+            .iter()
+            .min_by_key(|(_, (pr, pg, pb))| {
+                let dr = r as i32 - *pr as i32;
+                let dg = g as i32 - *pg as i32;
+                let db = b as i32 - *pb as i32;
+                dr * dr + dg * dg + db * db
+            })
+            .map(|(color, _)| *color)
+            .unwrap()
+    }
+
+    /// Degrade this color to the highest tier `level` supports.
+    pub(crate) fn degrade(self, level: ColorLevel) -> Color {
+        match level {
+            ColorLevel::TrueColor => self,
+            ColorLevel::Indexed256 => self.to_indexed256(),
+            ColorLevel::Ansi16 => self.to_ansi16(),
+        }
+    }
+}
+
+/// Emit `\x1b[{first_byte};…m`, the SGR form used to select a color.
+fn write_color(f: &mut fmt::Formatter, first_byte: usize, color: Color) -> fmt::Result {
+    match color {
+        Color::Standard(n) => write!(f, "\x1b[{};5;{}m", first_byte, n as usize),
+        Color::Bold(n) => write!(f, "\x1b[{};5;{}m", first_byte, n as usize + 8),
+        Color::Cube(r, g, b) => write!(
+            f,
+            "\x1b[{};5;{}m",
+            first_byte,
+            16 + 36 * r as usize + 6 * g as usize + b as usize
+        ),
+        Color::Grayscale(n) => write!(f, "\x1b[{};5;{}m", first_byte, n as usize + 232),
+        Color::True(r, g, b) => write!(
+            f,
+            "\x1b[{};2;{};{};{}m",
+            first_byte, r as usize, g as usize, b as usize
+        ),
+    }
+}
+
+/// Displaying a bare [`Color`] selects it as the foreground; wrap it in
+/// [`Style::Background`] to select it as a background instead.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_color(f, 38, *self)
+    }
+}
+
+/// Displaying a [`Style`] emits the SGR sequence that turns it on. Styles
+/// compose, so a whole frame can be buffered with `write!` before a single
+/// write to the terminal.
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Style::Foreground(color) => write_color(f, 38, *color),
+            Style::Background(color) => write_color(f, 48, *color),
+            Style::Bold => write!(f, "\x1b[1m"),
+            Style::Underlined => write!(f, "\x1b[4m"),
+            Style::Compound(styles) => styles.iter().try_for_each(|style| style.fmt(f)),
+        }
+    }
+}
+
+/// Resets all styling back to the terminal's default, emitting `\x1b[0m`.
+#[derive(Debug, Clone, Copy)]
+pub struct Reset;
+
+impl fmt::Display for Reset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\x1b[0m")
+    }
 }
 
 #[cfg(not(windows))]
 impl Console {
-    const SGR_FINAL_BYTE: char = 'm';
     pub const CTRL_C: u8 = 3;
     pub const BACKSPACE: u8 = 127;
     pub const ESC: u8 = 0o33;
@@ -79,7 +344,36 @@ impl Console {
         termios.c_lflag &= !(libc::ECHO | libc::ICANON | libc::IEXTEN | libc::ISIG);
         Self::set_termios(termios)?;
 
-        Ok(Self { prev_termios })
+        Ok(Self {
+            prev_termios,
+            sync_updates: true,
+            color_level: ColorLevel::detect(),
+        })
+    }
+
+    /// Force the color tier used when emitting colors, overriding the value
+    /// detected from the environment.
+    pub fn set_color_level(&mut self, level: ColorLevel) {
+        self.color_level = level;
+    }
+
+    /// Begin a synchronized update: a conforming terminal buffers everything
+    /// emitted until [`end_synchronized_update`](Self::end_synchronized_update)
+    /// and presents it atomically, so a full frame repaint never tears.
+    pub fn begin_synchronized_update(&mut self) -> io::Result<()> {
+        if self.sync_updates {
+            self.print_ansi(iter::once("?2026"), 'h')?;
+        }
+        Ok(())
+    }
+
+    /// End a synchronized update started with
+    /// [`begin_synchronized_update`](Self::begin_synchronized_update).
+    pub fn end_synchronized_update(&mut self) -> io::Result<()> {
+        if self.sync_updates {
+            self.print_ansi(iter::once("?2026"), 'l')?;
+        }
+        Ok(())
     }
 
     /// Print an ANSI control sequence
@@ -91,77 +385,55 @@ impl Console {
         /// Introduces a control sequence
         const CSI: &str = "\x1b[";
 
-        // Lock stdout so that the control sequence comes out right 100% of the time
-        let stdout = io::stdout();
-        let mut stdout_lock = stdout.lock();
+        // Lock stderr so that the control sequence comes out right 100% of the
+        // time; stdout is reserved for the final selection.
+        let stderr = io::stderr();
+        let mut stderr_lock = stderr.lock();
 
         // Write out the control sequence introducer
-        write!(stdout_lock, "{}", CSI)?;
+        write!(stderr_lock, "{}", CSI)?;
 
         // Write the first parameter normally and write every other parameter
         // preceded by a semicolon
-        let params = params.into_iter();
+        let mut params = params.into_iter();
         if let Some(param) = params.next() {
-            write!(stdout_lock, "{}", param)?;
+            write!(stderr_lock, "{}", param)?;
         }
-        params.try_for_each(|param| write!(stdout, ";{}", param))?;
+        params.try_for_each(|param| write!(stderr_lock, ";{}", param))?;
 
         // Print out the final byte that indicates what sequence we want to use
-        write!(stdout_lock, "{}", final_byte)?;
+        write!(stderr_lock, "{}", final_byte)?;
 
         Ok(())
     }
 
-    fn apply_color(&mut self, foreground: bool, color: &Color) -> io::Result<()> {
-        let first_byte = match foreground {
-            true => 38,
-            false => 48,
-        };
-
-        /// Print out \033[{first_byte};{params}m
-        macro_rules! doit {
-            [$($param:expr),*] => (self.print_ansi([first_byte $(,$param)*].iter().copied(), Self::SGR_FINAL_BYTE));
-        }
-
-        match *color {
-            Color::Standard(n) => {
-                assert!(n <= 7);
-                doit![5, n as usize]
-            }
-
-            Color::Bold(n) => {
-                assert!(n <= 7);
-                doit![5, n as usize + 8]
-            }
-
-            Color::Cube(r, g, b) => {
-                assert!(r <= 5);
-                assert!(g <= 5);
-                assert!(b <= 5);
-                doit![5, 16 + 36 * r as usize + 6 * g as usize + b as usize]
-            }
-
-            Color::Grayscale(n) => {
-                assert!(n <= 23);
-                doit![5, n as usize + 232]
+    /// Degrade every color in `style` to the highest tier this terminal can
+    /// render, leaving the non-color attributes untouched.
+    fn degrade_style(&self, style: &Style) -> Style {
+        match style {
+            Style::Foreground(color) => Style::Foreground(color.degrade(self.color_level)),
+            Style::Background(color) => Style::Background(color.degrade(self.color_level)),
+            Style::Bold => Style::Bold,
+            Style::Underlined => Style::Underlined,
+            Style::Compound(styles) => {
+                Style::Compound(styles.iter().map(|style| self.degrade_style(style)).collect())
             }
-
-            Color::True(r, g, b) => doit![2, r as usize, g as usize, b as usize],
         }
     }
 
     pub fn apply_style(&mut self, style: &Style) -> io::Result<()> {
-        match style {
-            Style::Foreground(color) => self.apply_color(true, color),
-            Style::Background(color) => self.apply_color(false, color),
-            Style::Bold => self.print_ansi(iter::once(1), Self::SGR_FINAL_BYTE),
-            Style::Underlined => self.print_ansi(iter::once(4), Self::SGR_FINAL_BYTE),
-            Style::Compound(styles) => styles.iter().map(|style| self.apply_style(style)).collect(),
-        }
+        // Degrade to the terminal's tier first, then let the `Display` impl
+        // build the CSI so the SGR logic lives in exactly one place.
+        let style = self.degrade_style(style);
+        let stderr = io::stderr();
+        let mut stderr_lock = stderr.lock();
+        write!(stderr_lock, "{}", style)
     }
 
     pub fn reset_all(&mut self) -> io::Result<()> {
-        self.print_ansi(iter::once(0), Self::SGR_FINAL_BYTE)
+        let stderr = io::stderr();
+        let mut stderr_lock = stderr.lock();
+        write!(stderr_lock, "{}", Reset)
     }
 
     /// Erase the current line and move the cursor to the beginning of it
@@ -171,6 +443,54 @@ impl Console {
         Ok(())
     }
 
+    /// Erase the current line in place, leaving the cursor where it is
+    pub fn clear_line(&mut self) -> io::Result<()> {
+        self.print_ansi(iter::once(2), 'K') // EL: Erase in line
+    }
+
+    /// Move the cursor to a zero-based column on the current line
+    pub fn move_to_column(&mut self, col: u16) -> io::Result<()> {
+        self.print_ansi(iter::once(col as usize + 1), 'G') // CHA
+    }
+
+    /// Move the cursor to the start of the line N up
+    pub fn move_up_n(&mut self, n: usize) -> io::Result<()> {
+        self.print_ansi(iter::once(n), 'F')
+    }
+
+    /// The terminal's `(columns, rows)`, queried via `TIOCGWINSZ`
+    pub fn window_size(&self) -> io::Result<(u16, u16)> {
+        unsafe {
+            let mut ws: libc::winsize = std::mem::zeroed();
+            if libc::ioctl(libc::STDERR_FILENO, libc::TIOCGWINSZ, &mut ws) == 0 {
+                Ok((ws.ws_col, ws.ws_row))
+            } else {
+                Err(io::Error::last_os_error())
+            }
+        }
+    }
+
+    /// Block until input is ready or `timeout` elapses, reporting whether a byte
+    /// can be read without blocking.
+    pub fn poll_input(&mut self, timeout: std::time::Duration) -> io::Result<bool> {
+        let mut pollfd = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ms = timeout.as_millis().min(i32::MAX as u128) as libc::c_int;
+        match unsafe { libc::poll(&mut pollfd, 1, ms) } {
+            -1 => Err(io::Error::last_os_error()),
+            0 => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    /// Flush buffered output to the terminal.
+    pub fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+
     pub fn move_down(&mut self) -> io::Result<()> {
         self.move_down_n(1)
     }
@@ -200,11 +520,120 @@ impl Console {
             }
         }
     }
+
+    /// Read one byte without blocking, returning `None` when nothing is ready.
+    ///
+    /// Used to tell a lone `ESC` apart from the start of an escape sequence: if
+    /// no byte follows immediately there's no sequence to decode.
+    fn read_one_char_ready(&mut self) -> io::Result<Option<u8>> {
+        let mut pollfd = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ready = unsafe { libc::poll(&mut pollfd, 1, 0) };
+        match ready {
+            -1 => Err(io::Error::last_os_error()),
+            0 => Ok(None),
+            _ => self.read_one_char().map(Some),
+        }
+    }
+
+    /// Read and decode a single key press, collapsing CSI/SS3 escape sequences
+    /// into [`Key`] variants. Mirrors the decoding table in the `console`
+    /// crate's `unix_term`.
+    pub fn read_key(&mut self) -> io::Result<Key> {
+        let byte = self.read_one_char()?;
+
+        match byte {
+            Self::CTRL_C => return Ok(Key::CtrlC),
+            Self::BACKSPACE | 0o10 => return Ok(Key::Backspace),
+            b'\t' => return Ok(Key::Tab),
+            b'\r' | b'\n' => return Ok(Key::Enter),
+            Self::ESC => {}
+            b if !b.is_ascii_control() => return Ok(Key::Char(b as char)),
+            // The remaining C0 control bytes are Ctrl-letter chords.
+            b @ 1..=26 => return Ok(Key::Ctrl((b - 1 + b'a') as char)),
+            b => return Ok(Key::Unknown(b)),
+        }
+
+        // We have an ESC; a `[` or `O` introduces a sequence, any other byte is
+        // reported as unknown, and nothing at all means the escape key was
+        // pressed on its own.
+        let introducer = match self.read_one_char_ready()? {
+            Some(b @ (b'[' | b'O')) => b,
+            Some(other) => return Ok(Key::Unknown(other)),
+            None => return Ok(Key::Esc),
+        };
+
+        // Collect parameter bytes (digits and `;`) up to the final alphabetic
+        // byte or the `~` that terminates the numbered sequences.
+        let mut params = Vec::new();
+        let final_byte = loop {
+            match self.read_one_char_ready()? {
+                Some(b) if b.is_ascii_digit() || b == b';' => params.push(b),
+                Some(b) => break b,
+                None => return Ok(Key::Esc),
+            }
+        };
+
+        let _ = introducer;
+        Ok(match final_byte {
+            b'A' => Key::Up,
+            b'B' => Key::Down,
+            b'C' => Key::Right,
+            b'D' => Key::Left,
+            b'H' => Key::Home,
+            b'F' => Key::End,
+            b'~' => match params.as_slice() {
+                b"1" | b"7" => Key::Home,
+                b"4" | b"8" => Key::End,
+                b"3" => Key::Delete,
+                b"5" => Key::PageUp,
+                b"6" => Key::PageDown,
+                _ => Key::Unknown(final_byte),
+            },
+            other => Key::Unknown(other),
+        })
+    }
 }
 
 #[cfg(not(windows))]
 impl Drop for Console {
     fn drop(&mut self) {
-        Self::set_termios(self.termios).expect("Could not restore termios")
+        Self::set_termios(self.prev_termios).expect("Could not restore termios")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hash_notation() {
+        assert_eq!(Color::parse("#ffffff"), Some(Color::True(255, 255, 255)));
+        assert_eq!(Color::parse("#000000"), Some(Color::True(0, 0, 0)));
+        assert_eq!(Color::parse("#ff8000"), Some(Color::True(255, 128, 0)));
+        // One digit per channel scales the same way as the wider forms.
+        assert_eq!(Color::parse("#f00"), Some(Color::True(255, 0, 0)));
+    }
+
+    #[test]
+    fn parse_rgb_notation() {
+        assert_eq!(Color::parse("rgb:ff/ff/ff"), Some(Color::True(255, 255, 255)));
+        assert_eq!(Color::parse("rgb:f/f/f"), Some(Color::True(255, 255, 255)));
+        // Groups may differ in width.
+        assert_eq!(Color::parse("rgb:ff/0/ffff"), Some(Color::True(255, 0, 255)));
+    }
+
+    #[test]
+    fn parse_rejects_malformed() {
+        assert_eq!(Color::parse("ffffff"), None);
+        assert_eq!(Color::parse("#fffff"), None);
+        assert_eq!(Color::parse("#"), None);
+        assert_eq!(Color::parse("#gg0000"), None);
+        assert_eq!(Color::parse("rgb:ff/ff"), None);
+        assert_eq!(Color::parse("rgb:ff/ff/ff/ff"), None);
+        assert_eq!(Color::parse("rgb:ff//ff"), None);
     }
 }
@@ -6,6 +6,7 @@ use std::{
 
 use structopt::StructOpt;
 
+mod console;
 mod selector;
 
 mod sliding_window;
@@ -16,6 +17,24 @@ struct Opt {
     #[structopt(short, long, default_value = "fuzzy")]
     mode: selector::SelectorMode,
 
+    /// How to break ties between equally-scored fuzzy matches (`shorter` or
+    /// `earlier`).
+    #[structopt(long, default_value = "shorter")]
+    tiebreak: selector::Tiebreak,
+
+    /// Force a color tier (`16`, `256`, or `truecolor`) instead of detecting
+    /// it from the environment.
+    #[structopt(long)]
+    color_mode: Option<console::ColorLevel>,
+
+    /// Select multiple items: Tab toggles a candidate, Enter prints all of them.
+    #[structopt(long)]
+    multi: bool,
+
+    /// Drive the screen with the built-in ANSI backend instead of crossterm.
+    #[structopt(long)]
+    native: bool,
+
     #[structopt(parse(from_os_str), default_value = "-")]
     words: PathBuf,
 }
@@ -31,10 +50,29 @@ fn main() {
         fs::read_to_string(opt.words).unwrap()
     };
 
-    let words = contents.lines().collect::<Vec<&str>>();
+    let words = contents.lines().map(str::to_owned).collect::<Vec<String>>();
 
-    ui::UI::new(selector::Selector::new(opt.mode, &words))
-        .unwrap()
-        .mainloop()
-        .unwrap();
+    // Honor an explicit `--color-mode`, otherwise sniff the terminal.
+    let color_level = opt.color_mode.unwrap_or_else(console::ColorLevel::detect);
+
+    let selector = selector::Selector::new(opt.mode, opt.tiebreak, words);
+
+    if opt.native {
+        #[cfg(not(windows))]
+        ui::UI::new(selector, ui::ConsoleBackend::new(color_level), opt.multi)
+            .unwrap()
+            .mainloop()
+            .unwrap();
+        #[cfg(windows)]
+        {
+            let _ = selector;
+            eprintln!("--native is unavailable on Windows; crossterm is the Windows backend");
+            std::process::exit(2);
+        }
+    } else {
+        ui::UI::new(selector, ui::CrosstermBackend::new(color_level), opt.multi)
+            .unwrap()
+            .mainloop()
+            .unwrap();
+    }
 }
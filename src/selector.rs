@@ -1,4 +1,10 @@
 use std::str::FromStr;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    mpsc::{self, Receiver, Sender},
+    Arc, Mutex,
+};
+use std::thread::{self, JoinHandle};
 
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
 
@@ -20,69 +26,272 @@ impl FromStr for SelectorMode {
     }
 }
 
-// TODO: It might be interesting to use Pin<_> to make this own its items.
-#[derive(Debug)]
-pub struct Selector<'a> {
-    mode: SelectorMode,
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub item: String,
+    pub highlight: Vec<(usize, usize)>,
 
-    /// All of the items
-    items: &'a [&'a str],
+    /// The fuzzy score this item earned against the pattern; higher is a better
+    /// match. `FixedString` matches carry a score of `0` and are never sorted.
+    pub score: i64,
 
-    /// A vector of matches, which are represented as an index into items and a range
-    matches: Vec<Match<'a>>,
+    /// The item's position in the original input, used as the final, stable
+    /// tiebreak so equally-scored matches keep a deterministic order.
+    pub index: usize,
 }
 
-#[derive(Debug)]
-pub struct Match<'a> {
-    pub item: &'a str,
-    pub highlight: Vec<(usize, usize)>,
+/// How to break ties between matches that earned the same fuzzy score.
+///
+/// `FixedString` mode ignores this and preserves input order; it only applies
+/// to the `Fuzzy` arm, where many candidates routinely tie.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Tiebreak {
+    /// Prefer the shorter item, so a tight match floats above a loose one.
+    #[default]
+    ShorterFirst,
+
+    /// Prefer the item whose first matched character appears earliest.
+    EarlierMatch,
 }
 
-impl<'a> Selector<'a> {
-    pub fn new(mode: SelectorMode, items: &'a [&'a str]) -> Self {
+impl FromStr for Tiebreak {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "shorter" => Ok(Self::ShorterFirst),
+            "earlier" => Ok(Self::EarlierMatch),
+            _ => Err("expected shorter or earlier"),
+        }
+    }
+}
+
+/// The shared result buffer the worker writes into and the UI reads from.
+///
+/// `generation` tags the pattern the matches belong to so a repaint can tell
+/// whether what it's about to draw is stale.
+#[derive(Debug, Default)]
+struct Snapshot {
+    matches: Vec<Match>,
+    generation: usize,
+    done: bool,
+}
+
+/// How many matches the worker accumulates before publishing them and pinging
+/// the UI, trading repaint frequency against lock traffic.
+const BATCH: usize = 512;
+
+/// A fuzzy/fixed selector backed by a background matching thread.
+///
+/// `set_pattern` hands the new pattern (and a monotonically increasing
+/// generation) to the worker; the worker rescans `items`, streaming results
+/// into a shared [`Snapshot`] and pinging `updates` as it goes. A newer pattern
+/// bumps the generation, which the worker checks between items so a stale scan
+/// is abandoned mid-flight. The keystroke path therefore never blocks on the
+/// full corpus, and the `SkimMatcherV2` is built once and reused across queries.
+pub struct Selector {
+    pattern_tx: Sender<(usize, String)>,
+    generation: Arc<AtomicUsize>,
+    next_generation: usize,
+    snapshot: Arc<Mutex<Snapshot>>,
+    updates: Receiver<()>,
+
+    /// A local materialization of the latest snapshot, so `matches` can hand out
+    /// a borrow without holding the lock.
+    current: Vec<Match>,
+    current_generation: usize,
+
+    _worker: JoinHandle<()>,
+}
+
+impl Selector {
+    pub fn new(mode: SelectorMode, tiebreak: Tiebreak, items: Vec<String>) -> Self {
+        let items: Arc<[String]> = items.into();
+        let generation = Arc::new(AtomicUsize::new(0));
+        let snapshot = Arc::new(Mutex::new(Snapshot::default()));
+
+        let (pattern_tx, pattern_rx) = mpsc::channel::<(usize, String)>();
+        let (update_tx, updates) = mpsc::channel::<()>();
+
+        let worker = {
+            let generation = Arc::clone(&generation);
+            let snapshot = Arc::clone(&snapshot);
+            thread::spawn(move || {
+                worker(mode, tiebreak, items, pattern_rx, generation, snapshot, update_tx)
+            })
+        };
+
         let mut this = Self {
-            mode,
-            items,
-            matches: Vec::new(),
+            pattern_tx,
+            generation,
+            next_generation: 0,
+            snapshot,
+            updates,
+            current: Vec::new(),
+            current_generation: 0,
+            _worker: worker,
         };
+
+        // Kick off the initial (empty-pattern) pass so there's something to draw.
         this.set_pattern("");
         this
     }
 
-    pub fn matches(&'a self) -> &'a [Match<'a>] {
-        self.matches.as_ref()
+    /// The matches materialized by the most recent [`poll`](Self::poll).
+    pub fn matches(&self) -> &[Match] {
+        &self.current
+    }
+
+    /// A signal that fires whenever the worker has published new results; the
+    /// mainloop can block on this alongside key events.
+    pub fn updates(&self) -> &Receiver<()> {
+        &self.updates
     }
 
+    /// Relay a new pattern to the worker, cancelling any in-flight scan.
     pub fn set_pattern(&mut self, pattern: &str) {
-        self.matches = match self.mode {
-            SelectorMode::FixedString => self
-                .items
-                .iter()
-                .filter_map(|item| {
-                    item.find(pattern).map(|start| Match {
-                        item,
-                        highlight: vec![(start, start + pattern.len())],
-                    })
-                })
-                .collect(),
-
-            SelectorMode::Fuzzy => {
-                let matcher = SkimMatcherV2::default();
-
-                self.items
-                    .iter()
-                    .filter_map(|item| {
-                        let (_, indices) = matcher.fuzzy_indices(item, pattern)?;
-
-                        Some(Match {
-                            item,
-                            highlight: indices.into_iter().map(|idx| (idx, idx + 1)).collect(),
-                        })
-                    })
-                    .collect()
+        self.next_generation += 1;
+        // Publishing the new generation first makes the worker drop the old scan
+        // at its next item boundary.
+        self.generation.store(self.next_generation, Ordering::SeqCst);
+        // If the worker has gone away the UI is about to tear down too, so the
+        // dropped pattern is harmless.
+        let _ = self.pattern_tx.send((self.next_generation, pattern.to_owned()));
+    }
+
+    /// Copy whatever the worker has published so far into `current`, returning
+    /// whether anything changed. Never blocks.
+    pub fn poll(&mut self) -> bool {
+        // Drain the ping channel so it doesn't back up; one refresh covers all.
+        let mut pinged = false;
+        while self.updates.try_recv().is_ok() {
+            pinged = true;
+        }
+        if !pinged && self.current_generation == self.next_generation {
+            return false;
+        }
+
+        let snapshot = self.snapshot.lock().unwrap();
+        if snapshot.generation != self.next_generation {
+            // The worker hasn't caught up to our latest pattern yet.
+            return false;
+        }
+        self.current = snapshot.matches.clone();
+        self.current_generation = snapshot.generation;
+        true
+    }
+}
+
+/// Compute the match for a single item against `pattern`, if any.
+fn match_item(
+    mode: SelectorMode,
+    matcher: &SkimMatcherV2,
+    index: usize,
+    item: &str,
+    pattern: &str,
+) -> Option<Match> {
+    match mode {
+        SelectorMode::FixedString => item.find(pattern).map(|byte| {
+            // The highlight range is expressed in `char` indices so the renderer
+            // can slice on character boundaries; `find` gives a byte offset.
+            let start = item[..byte].chars().count();
+            Match {
+                item: item.to_owned(),
+                highlight: vec![(start, start + pattern.chars().count())],
+                score: 0,
+                index,
             }
+        }),
+
+        SelectorMode::Fuzzy => {
+            let (score, indices) = matcher.fuzzy_indices(item, pattern)?;
+            Some(Match {
+                item: item.to_owned(),
+                highlight: indices.into_iter().map(|idx| (idx, idx + 1)).collect(),
+                score,
+                index,
+            })
         }
     }
 }
 
-// FIXME: write tests
+/// Order two matches best-first: by descending score, then by `tiebreak`, then
+/// by original index so the result is fully deterministic.
+fn compare(a: &Match, b: &Match, tiebreak: Tiebreak) -> std::cmp::Ordering {
+    b.score
+        .cmp(&a.score)
+        .then_with(|| match tiebreak {
+            Tiebreak::ShorterFirst => a.item.chars().count().cmp(&b.item.chars().count()),
+            Tiebreak::EarlierMatch => {
+                let first = |m: &Match| m.highlight.first().map_or(usize::MAX, |&(start, _)| start);
+                first(a).cmp(&first(b))
+            }
+        })
+        .then_with(|| a.index.cmp(&b.index))
+}
+
+/// The background matching loop: scan `items` for each incoming pattern,
+/// abandoning the scan as soon as a newer generation appears.
+fn worker(
+    mode: SelectorMode,
+    tiebreak: Tiebreak,
+    items: Arc<[String]>,
+    pattern_rx: Receiver<(usize, String)>,
+    generation: Arc<AtomicUsize>,
+    snapshot: Arc<Mutex<Snapshot>>,
+    update_tx: Sender<()>,
+) {
+    let matcher = SkimMatcherV2::default();
+
+    // Publish the accumulated results for generation `gen`, sorting them
+    // best-first in `Fuzzy` mode. `FixedString` has nothing to rank, so it keeps
+    // input order and skips the sort entirely.
+    let publish = |results: &[Match], gen: usize, done: bool| {
+        let mut ranked = results.to_vec();
+        if matches!(mode, SelectorMode::Fuzzy) {
+            ranked.sort_by(|a, b| compare(a, b, tiebreak));
+        }
+        let mut snap = snapshot.lock().unwrap();
+        snap.matches = ranked;
+        snap.generation = gen;
+        snap.done = done;
+        drop(snap);
+        let _ = update_tx.send(());
+    };
+
+    for (gen, pattern) in pattern_rx.iter() {
+        // A fresher pattern is already queued; skip straight to it.
+        if generation.load(Ordering::SeqCst) != gen {
+            continue;
+        }
+
+        // Start a clean snapshot for this generation.
+        publish(&[], gen, false);
+
+        let mut results = Vec::new();
+        let mut pending = 0;
+        let mut cancelled = false;
+        for (index, item) in items.iter().enumerate() {
+            if generation.load(Ordering::SeqCst) != gen {
+                cancelled = true;
+                break;
+            }
+
+            if let Some(m) = match_item(mode, &matcher, index, item, &pattern) {
+                results.push(m);
+                pending += 1;
+            }
+
+            if pending >= BATCH {
+                publish(&results, gen, false);
+                pending = 0;
+            }
+        }
+
+        if cancelled {
+            continue;
+        }
+
+        publish(&results, gen, true);
+    }
+}
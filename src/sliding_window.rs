@@ -14,14 +14,40 @@ impl SlidingWindow {
         Self { size, offset: 0 }
     }
 
+    /// The largest offset that still fills the window given `len` items
+    fn max_offset(&self, len: usize) -> usize {
+        len - min(self.size, len)
+    }
+
     /// Scroll the window down by one
-    pub fn scroll_down(&mut self) {
-        self.offset += 1;
+    pub fn scroll_down(&mut self, len: usize) {
+        self.scroll_down_n(1, len);
     }
 
     /// Scroll the window up by one
     pub fn scroll_up(&mut self) {
-        self.offset = self.offset.saturating_sub(1);
+        self.scroll_up_n(1);
+    }
+
+    /// Scroll the window down by `n`, clamped so the offset never runs past the
+    /// last full window of `len` items
+    pub fn scroll_down_n(&mut self, n: usize, len: usize) {
+        self.offset = min(self.offset + n, self.max_offset(len));
+    }
+
+    /// Scroll the window up by `n`
+    pub fn scroll_up_n(&mut self, n: usize) {
+        self.offset = self.offset.saturating_sub(n);
+    }
+
+    /// Scroll down by a full window
+    pub fn page_down(&mut self, len: usize) {
+        self.scroll_down_n(self.size, len);
+    }
+
+    /// Scroll up by a full window
+    pub fn page_up(&mut self) {
+        self.scroll_up_n(self.size);
     }
 
     /// Apply the window to a given slice
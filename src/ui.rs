@@ -1,305 +1,390 @@
 use std::{
     cmp::{max, min},
-    io::{self, prelude::*},
+    collections::HashMap,
+    io,
+    time::Duration,
 };
 
+use crate::console::{Color, Key};
 use crate::selector::{Match, Selector};
 use crate::sliding_window::SlidingWindow;
 
-use crossterm::{
-    cursor::{MoveToColumn, MoveToNextLine, MoveToPreviousLine, RestorePosition, SavePosition},
-    event::{Event, KeyCode, KeyModifiers},
-    queue,
-    style::{Attribute, Color, ContentStyle, Print, PrintStyledContent},
-    terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType},
-};
+mod backend;
+mod frame;
+mod line_buffer;
+mod text;
+
+pub use backend::{Backend, CrosstermBackend};
+#[cfg(not(windows))]
+pub use backend::ConsoleBackend;
+
+use backend::{CellStyle, Event};
+use frame::{Frame, Span};
+use line_buffer::LineBuffer;
+
+pub struct UI<B: Backend> {
+    backend: B,
 
-pub struct UI<'a> {
     prompt: String,
+    pattern: LineBuffer,
 
-    selector: Selector<'a>,
-    match_amount: usize,
+    selector: Selector,
 
     selected: usize,
 
+    /// Whether Tab toggles candidates into a selection set printed all at once.
+    multi: bool,
+
+    /// The items toggled on in multi-select mode, keyed by their text and
+    /// carrying their original input index so the output can be ordered even
+    /// after the query has changed out from under them.
+    chosen: HashMap<String, usize>,
+
     window: SlidingWindow,
 
-    selected_style: ContentStyle,
-    highlight_style: ContentStyle,
-}
+    /// The terminal's current width in columns; rendered rows are truncated to
+    /// it so a long candidate never wraps and tears the layout.
+    columns: u16,
 
-fn merge(a: ContentStyle, b: ContentStyle) -> ContentStyle {
-    ContentStyle {
-        foreground_color: b.foreground_color.or(a.foreground_color),
-        background_color: b.background_color.or(a.background_color),
-        attributes: a.attributes | b.attributes,
-    }
+    /// The last frame we actually drew, diffed against on every repaint so we
+    /// only touch the rows that changed.
+    previous: Frame,
+
+    selected_style: CellStyle,
+    highlight_style: CellStyle,
+    marker_style: CellStyle,
 }
 
-fn calculate_window_size() -> crossterm::Result<usize> {
-    let (_, row) = crossterm::cursor::position()?;
-    let (_, h) = crossterm::terminal::size()?;
+/// Work out how many candidate rows fit below the prompt, scrolling the prompt
+/// up if the terminal is too close to the bottom edge to leave room.
+fn calculate_window_size<B: Backend>(backend: &mut B) -> io::Result<usize> {
+    let (_, h) = backend.window_size()?;
+    let row = backend.cursor_row()?;
 
     let below = (h - (row + 1)) as usize;
 
-    let stderr_lock = io::stderr();
-    let mut stderr = stderr_lock.lock();
     for _ in below..2 {
-        queue!(stderr, MoveToPreviousLine(1), Clear(ClearType::CurrentLine))?;
+        backend.move_to_previous_line(1)?;
+        backend.clear_current_line()?;
     }
-    stderr.flush()?;
+    backend.flush()?;
 
     Ok(min(max(below, 2), 20))
 }
 
-impl<'a> UI<'a> {
-    pub fn new(selector: Selector<'a>) -> crossterm::Result<Self> {
+impl<B: Backend> UI<B> {
+    pub fn new(selector: Selector, mut backend: B, multi: bool) -> io::Result<Self> {
+        let window = SlidingWindow::new(calculate_window_size(&mut backend)?);
+        let (columns, _) = backend.window_size()?;
+
         Ok(Self {
+            backend,
+
             prompt: "> ".to_owned(),
+            pattern: LineBuffer::new(),
 
             selector,
-            match_amount: 0,
 
             selected: 0,
 
-            window: SlidingWindow::new(calculate_window_size()?),
+            multi,
+            chosen: HashMap::new(),
+
+            window,
+            columns,
+
+            previous: Frame::new(),
 
-            selected_style: ContentStyle::new().background(Color::AnsiValue(1)),
-            highlight_style: ContentStyle::new()
-                .attribute(Attribute::Bold)
-                .attribute(Attribute::Underlined),
+            selected_style: CellStyle::new().background(Color::Standard(1)),
+            highlight_style: CellStyle::new().bold().underline(),
+            marker_style: CellStyle::new().foreground(Color::Standard(2)).bold(),
         })
     }
 
-    fn print_prompt(&mut self) -> crossterm::Result<()> {
-        queue!(io::stderr(), Print(&self.prompt))
-    }
+    /// Build the spans for a single match row, honoring the highlight ranges and
+    /// the selected-row background.
+    fn match_spans(&self, selected: bool, Match { item, highlight, .. }: &Match) -> Vec<Span> {
+        let base = if selected {
+            self.selected_style
+        } else {
+            CellStyle::new()
+        };
+
+        let mut spans = Vec::new();
+
+        // In multi-select mode every row carries a marker column showing whether
+        // it is toggled into the selection set.
+        if self.multi {
+            let glyph = if self.chosen.contains_key(item) { "> " } else { "  " };
+            spans.push(Span::new(glyph, self.marker_style.merge(base)));
+        }
 
-    /// Print out a match, taking care of highlighting, on the current line
-    fn print_match(
-        &self,
-        selected: bool,
-        Match { item, highlight }: &Match<'a>,
-    ) -> crossterm::Result<()> {
-        let stderr_lock = io::stderr();
-        let mut stderr = stderr_lock.lock();
-
-        // Erase anything that's in the line
-        queue!(stderr, Clear(ClearType::CurrentLine), MoveToColumn(0))?;
-
-        let mut print = move |style: Option<ContentStyle>, s| -> crossterm::Result<()> {
-            let style = merge(
-                style.unwrap_or(ContentStyle::new()),
-                if selected {
-                    self.selected_style
-                } else {
-                    ContentStyle::new()
-                },
-            );
-
-            queue!(stderr, PrintStyledContent(style.apply(s)))
+        let mut push = |style: Option<CellStyle>, s: &str| {
+            if s.is_empty() {
+                return;
+            }
+            spans.push(Span::new(s, style.unwrap_or_default().merge(base)));
         };
 
-        let end =
-            highlight
+        // Highlight ranges are `char` indices, so slice the item by character
+        // boundaries rather than raw bytes; a byte slice would panic on any
+        // multibyte (CJK/emoji) candidate.
+        let chars: Vec<char> = item.chars().collect();
+        let slice = |range: std::ops::Range<usize>| -> String {
+            chars[range.start.min(chars.len())..range.end.min(chars.len())]
                 .iter()
-                .try_fold(0, |last, &(start, end)| -> crossterm::Result<usize> {
-                    // Print out the stuff between highlight groups normally
-                    print(None, &item[last..start])?;
-
-                    // Print the inside of the group with the highlight style
-                    print(Some(self.highlight_style), &item[start..end])?;
+                .collect()
+        };
 
-                    // Pass on the ball
-                    Ok(end)
-                })?;
+        // Print the stuff between highlight groups normally and the inside of
+        // each group with the highlight style.
+        let end = highlight.iter().fold(0, |last, &(start, end)| {
+            push(None, &slice(last..start));
+            push(Some(self.highlight_style), &slice(start..end));
+            end
+        });
 
-        // Print out what's leftover normally
-        print(None, &item[end..])?;
+        // Whatever's leftover after the last group goes out normally.
+        push(None, &slice(end..chars.len()));
 
-        Ok(())
+        spans
     }
 
-    /// Print out the current matcheson the line below the current one, restoring the cursor
-    /// position afterwards
-    fn print_items(&mut self) -> crossterm::Result<()> {
-        let stderr_lock = io::stderr();
-        let mut stderr = stderr_lock.lock();
-
-        queue!(stderr, SavePosition, MoveToNextLine(1))?;
+    /// Assemble the desired screen state: the prompt line on top, the visible
+    /// slice of matches below it.
+    fn build_frame(&mut self) -> Frame {
+        // Pull in whatever the background worker has produced since last paint.
+        self.selector.poll();
 
         let matches = self.window.apply(self.selector.matches());
-        let match_amount = matches.len();
 
-        if self.selected >= match_amount {
-            self.selected = match_amount.saturating_sub(1);
+        if self.selected >= matches.len() {
+            self.selected = matches.len().saturating_sub(1);
         }
 
+        let mut frame = Frame::new();
+        frame.push_row(vec![Span::new(
+            format!("{}{}", self.prompt, self.pattern.as_str()),
+            CellStyle::new(),
+        )]);
         for (index, match_) in matches.iter().enumerate() {
-            // Erase any leftovers in the line
-            self.print_match(index == self.selected, match_)?;
-            queue!(stderr, MoveToNextLine(1))?;
+            let spans = self.match_spans(index == self.selected, match_);
+            frame.push_row(text::truncate_spans(spans, self.columns as usize));
         }
+        frame
+    }
 
-        // Clear out any leftover lines
-        for _ in 0..(self.match_amount.saturating_sub(match_amount)) {
-            queue!(stderr, Clear(ClearType::CurrentLine), MoveToNextLine(1))?;
-        }
-        self.match_amount = match_amount;
+    /// Render the current state by diffing a freshly built frame against the one
+    /// last displayed and leaving the caret at the query's cursor position.
+    fn render(&mut self) -> io::Result<()> {
+        let frame = self.build_frame();
+
+        // Bracket the whole repaint in a synchronized update so a slow terminal
+        // composites the finished frame at once instead of tearing through the
+        // half-cleared intermediate rows.
+        self.backend.begin_sync()?;
 
-        queue!(stderr, RestorePosition)?;
+        // `reconcile` saves and restores the caret around the prompt row's
+        // start, so anchor it there first.
+        self.backend.move_to_column(0)?;
+        frame.reconcile(&self.previous, &mut self.backend)?;
 
-        stderr.flush()?;
+        // Drop the caret onto its column within the query line.
+        let caret = self.prompt.chars().count() + self.pattern.caret();
+        self.backend.move_to_column(caret as u16)?;
 
+        self.backend.end_sync()?;
+        self.backend.flush()?;
+        self.previous = frame;
         Ok(())
     }
 
-    pub fn mainloop(mut self) -> crossterm::Result<()> {
-        enable_raw_mode()?;
+    /// Number of match rows currently on screen.
+    fn visible(&mut self) -> usize {
+        self.window.apply(self.selector.matches()).len()
+    }
+
+    /// The text of the currently highlighted candidate, if any.
+    fn selected_item(&mut self) -> Option<String> {
+        self.window
+            .apply(self.selector.matches())
+            .get(self.selected)
+            .map(|Match { item, .. }| item.clone())
+    }
+
+    /// The highlighted candidate's text and original input index, used to record
+    /// a toggle that has to outlive later query changes.
+    fn selected_entry(&mut self) -> Option<(String, usize)> {
+        self.window
+            .apply(self.selector.matches())
+            .get(self.selected)
+            .map(|Match { item, index, .. }| (item.clone(), *index))
+    }
 
-        let stderr_lock = io::stderr();
-        let mut stderr = stderr_lock.lock();
+    pub fn mainloop(mut self) -> io::Result<()> {
+        self.backend.enter_raw_mode()?;
 
-        self.print_prompt()?;
-        self.print_items()?;
+        self.render()?;
 
-        let mut pattern = String::new();
         loop {
-            let key = match crossterm::event::read()? {
-                Event::Key(evt) => evt,
-                Event::Mouse(_) | Event::Resize(_, _) => continue,
-            };
+            // Wake on either a key press or, failing that, a fresh batch of
+            // matches streaming in from the worker thread.
+            if !self.backend.poll_event(Duration::from_millis(50))? {
+                if self.selector.poll() {
+                    self.render()?;
+                }
+                continue;
+            }
 
-            match key.code {
-                KeyCode::Enter | KeyCode::Esc => {
-                    break;
+            let key = match self.backend.read_event()? {
+                Event::Key(key) => key,
+
+                // A resize invalidates the window geometry computed at startup,
+                // so recompute it, reclamp the cursor, and repaint from scratch.
+                Event::Resize(w, _) => {
+                    self.columns = w;
+                    self.window = SlidingWindow::new(calculate_window_size(&mut self.backend)?);
+                    let visible = self.visible();
+                    if self.selected >= visible {
+                        self.selected = visible.saturating_sub(1);
+                    }
+                    self.previous = Frame::new();
+                    self.render()?;
+                    continue;
                 }
+            };
 
-                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            match key {
+                Key::Enter | Key::Esc | Key::CtrlC => {
                     break;
                 }
 
-                // If the user inputs a backspace ...
-                KeyCode::Backspace => {
-                    // ... remove the latest character and relay the change to the selector ...
-                    pattern.pop();
-                    self.selector.set_pattern(&pattern);
-
-                    // ... then clear out the prompt line ...
-                    queue!(stderr, Clear(ClearType::CurrentLine), MoveToColumn(0))?;
+                // Toggle the highlighted candidate in and out of the selection.
+                Key::Tab if self.multi => {
+                    if let Some((item, index)) = self.selected_entry() {
+                        if self.chosen.remove(&item).is_none() {
+                            self.chosen.insert(item, index);
+                        }
+                    }
+                    self.render()?;
+                }
 
-                    // ... and print it out again ...
-                    self.print_prompt()?;
+                // Remove the character before the cursor and relay the change to
+                // the selector.
+                Key::Backspace => {
+                    self.pattern.backspace();
+                    self.selector.set_pattern(self.pattern.as_str());
+                    self.render()?;
+                }
 
-                    queue!(stderr, Print(&pattern))?;
+                // Remove the character under the cursor.
+                Key::Delete => {
+                    self.pattern.delete();
+                    self.selector.set_pattern(self.pattern.as_str());
+                    self.render()?;
+                }
 
-                    // ... then print out the new matches
-                    self.print_items()?;
+                // Delete the previous word.
+                Key::Ctrl('w') => {
+                    self.pattern.delete_prev_word();
+                    self.selector.set_pattern(self.pattern.as_str());
+                    self.render()?;
+                }
 
-                    stderr.flush()?;
+                // Kill from the cursor back to the start of the line.
+                Key::Ctrl('u') => {
+                    self.pattern.kill_to_start();
+                    self.selector.set_pattern(self.pattern.as_str());
+                    self.render()?;
                 }
 
-                key @ KeyCode::Up | key @ KeyCode::Down => {
-                    let matches = self.window.apply(self.selector.matches());
+                // Cursor movement within the query line; the matches don't change.
+                Key::Left => {
+                    self.pattern.move_left();
+                    self.render()?;
+                }
+                Key::Right => {
+                    self.pattern.move_right();
+                    self.render()?;
+                }
+                Key::Home | Key::Ctrl('a') => {
+                    self.pattern.move_home();
+                    self.render()?;
+                }
+                Key::End | Key::Ctrl('e') => {
+                    self.pattern.move_end();
+                    self.render()?;
+                }
 
-                    if matches.is_empty() {
-                        continue;
+                Key::Up => {
+                    if self.selected == 0 {
+                        self.window.scroll_up();
+                    } else {
+                        self.selected -= 1;
                     }
+                    self.render()?;
+                }
 
-                    // Draw the previously selected line as unselected
-                    queue!(
-                        io::stderr(),
-                        SavePosition,
-                        MoveToNextLine((self.selected + 1) as u16)
-                    )?;
-                    self.print_match(false, &matches[self.selected])?;
-                    queue!(io::stderr(), RestorePosition)?;
-
-                    // Move the selection
-                    if key == KeyCode::Up {
-                        // We're going up
-                        if self.selected == 0 {
-                            // If we're already at the top of the screen, scroll up
-                            self.window.scroll_up();
-                            self.print_items()?;
-                            stderr.flush()?;
-                            continue;
-                        }
-
-                        self.selected -= 1;
+                Key::Down => {
+                    if self.selected + 1 >= self.visible() {
+                        self.window.scroll_down(self.selector.matches().len());
                     } else {
-                        // We're going down
-                        // If we're already at the end of the list, scroll down
-                        if self.selected == self.match_amount.saturating_sub(1) {
-                            self.window.scroll_down();
-                            self.print_items()?;
-                            stderr.flush()?;
-                            continue;
-                        }
-
                         self.selected += 1;
                     }
-
-                    // Draw the new selected line
-                    queue!(
-                        stderr,
-                        SavePosition,
-                        MoveToNextLine((self.selected + 1) as u16)
-                    )?;
-                    self.print_match(true, &matches[self.selected])?;
-                    queue!(stderr, RestorePosition)?;
-
-                    // Update the display
-                    stderr.flush()?;
+                    self.render()?;
                 }
 
-                // If the character is printable ...
-                KeyCode::Char(ch) => {
-                    // ... push it to the pattern and relay the change to the selector ...
-                    pattern.push(ch);
-                    self.selector.set_pattern(&pattern);
-
-                    // ... echo it to the user ...
-                    queue!(stderr, Print(ch))?;
-
-                    // ... and print out the new matches
-                    self.print_items()?;
+                // Insert a printable character at the cursor and relay the change
+                // to the selector.
+                Key::Char(ch) => {
+                    self.pattern.insert(ch);
+                    self.selector.set_pattern(self.pattern.as_str());
+                    self.render()?;
+                }
 
-                    stderr.flush()?;
+                // Scroll a whole window at a time, clamping the cursor to
+                // whatever stays visible afterwards.
+                Key::PageUp => {
+                    self.window.page_up();
+                    self.render()?;
+                }
+                Key::PageDown => {
+                    self.window.page_down(self.selector.matches().len());
+                    let visible = self.visible();
+                    if self.selected >= visible {
+                        self.selected = visible.saturating_sub(1);
+                    }
+                    self.render()?;
                 }
 
-                KeyCode::Left
-                | KeyCode::Right
-                | KeyCode::Home
-                | KeyCode::End
-                | KeyCode::PageUp
-                | KeyCode::PageDown
-                | KeyCode::Tab
-                | KeyCode::BackTab
-                | KeyCode::Delete
-                | KeyCode::Insert
-                | KeyCode::F(_)
-                | KeyCode::Null => {}
+                Key::Tab | Key::Ctrl(_) | Key::Unknown(_) => {}
             }
         }
 
-        disable_raw_mode()?;
-
-        if let Some(Match { item, .. }) =
-            (self.window.apply(self.selector.matches())).get(self.selected)
-        {
-            queue!(
-                stderr,
-                Clear(ClearType::CurrentLine),
-                MoveToColumn(0),
-                SavePosition
-            )?;
-            for _ in 0..self.match_amount {
-                queue!(stderr, MoveToNextLine(1), Clear(ClearType::CurrentLine))?;
-            }
-            queue!(stderr, RestorePosition)?;
-            stderr.flush()?;
+        // Gather the output before tearing the list down: every explicitly
+        // toggled item in input order, or the highlighted one as a fallback.
+        let output: Vec<String> = if self.multi && !self.chosen.is_empty() {
+            // Print every toggled item, including ones that no longer match the
+            // final query, ordered by their original position in the input.
+            let mut chosen: Vec<(usize, String)> = self
+                .chosen
+                .iter()
+                .map(|(item, &index)| (index, item.clone()))
+                .collect();
+            chosen.sort();
+            chosen.into_iter().map(|(_, item)| item).collect()
+        } else {
+            self.selected_item().into_iter().collect()
+        };
+
+        self.backend.leave_raw_mode()?;
+
+        // Erase the whole rendered region by reconciling against an empty frame.
+        self.backend.move_to_column(0)?;
+        Frame::new().reconcile(&self.previous, &mut self.backend)?;
+        self.backend.flush()?;
 
+        for item in output {
             // NB: This prints to stdout, not to the console
             println!("{}", item);
         }
@@ -0,0 +1,415 @@
+//! A pluggable terminal backend.
+//!
+//! The crate carries two terminal layers: the [`crossterm`]-based one the `UI`
+//! grew up around, and the hand-rolled ANSI [`Console`](crate::console::Console)
+//! with its own termios raw-mode handling. This trait factors out exactly the
+//! operations the UI needs so either can drive the screen, reusing the native
+//! `apply_style`/`erase_line` code behind the same interface. The backend is
+//! picked at runtime; crossterm is always compiled in.
+//!
+//! The native `Console` is a Unix-only termios backend, so [`ConsoleBackend`]
+//! exists only there; on Windows the VT console is driven through crossterm,
+//! which is the platform's sole backend.
+
+use std::io;
+use std::time::Duration;
+
+use crate::console::{Color as TermColor, ColorLevel, Key};
+#[cfg(not(windows))]
+use crate::console::{Console, Style};
+
+/// A backend-neutral cell style: the foreground/background colors plus the two
+/// text attributes the UI actually emits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CellStyle {
+    pub foreground: Option<TermColor>,
+    pub background: Option<TermColor>,
+    pub bold: bool,
+    pub underline: bool,
+}
+
+impl CellStyle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn foreground(mut self, color: TermColor) -> Self {
+        self.foreground = Some(color);
+        self
+    }
+
+    pub fn background(mut self, color: TermColor) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    /// Overlay `other` on top of `self`: its colors win where set, attributes
+    /// accumulate.
+    pub fn merge(self, other: CellStyle) -> CellStyle {
+        CellStyle {
+            foreground: other.foreground.or(self.foreground),
+            background: other.background.or(self.background),
+            bold: self.bold || other.bold,
+            underline: self.underline || other.underline,
+        }
+    }
+}
+
+/// An input event, abstracted over the backend.
+///
+/// The native `Console` can only ever produce key presses; crossterm also
+/// surfaces resizes, which the UI uses to recompute its geometry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Key(Key),
+    Resize(u16, u16),
+}
+
+/// The terminal operations the `UI` drives the screen with.
+pub trait Backend {
+    /// Put the terminal into raw mode.
+    fn enter_raw_mode(&mut self) -> io::Result<()>;
+
+    /// Restore the terminal to its previous mode.
+    fn leave_raw_mode(&mut self) -> io::Result<()>;
+
+    /// The terminal size as `(columns, rows)`.
+    fn window_size(&mut self) -> io::Result<(u16, u16)>;
+
+    /// The cursor's current zero-based row.
+    fn cursor_row(&mut self) -> io::Result<u16>;
+
+    /// Wait up to `timeout` for an event, reporting whether one is ready.
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<bool>;
+
+    /// Read the next input event, blocking until one arrives.
+    fn read_event(&mut self) -> io::Result<Event>;
+
+    fn move_to_column(&mut self, col: u16) -> io::Result<()>;
+    fn move_to_next_line(&mut self, n: u16) -> io::Result<()>;
+    fn move_to_previous_line(&mut self, n: u16) -> io::Result<()>;
+    fn clear_current_line(&mut self) -> io::Result<()>;
+    fn save_position(&mut self) -> io::Result<()>;
+    fn restore_position(&mut self) -> io::Result<()>;
+
+    /// Print a run of text in the given style.
+    fn print_styled(&mut self, text: &str, style: &CellStyle) -> io::Result<()>;
+
+    /// Begin a synchronized update so the terminal composites the whole frame
+    /// atomically instead of tearing through the intermediate states.
+    fn begin_sync(&mut self) -> io::Result<()>;
+
+    /// End the synchronized update started by [`begin_sync`](Backend::begin_sync).
+    fn end_sync(&mut self) -> io::Result<()>;
+
+    /// Flush everything queued since the last flush.
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+/// The private-mode sequences that bracket a synchronized update (DEC mode
+/// 2026). Terminals that don't understand them ignore them harmlessly.
+const BEGIN_SYNC: &str = "\x1b[?2026h";
+const END_SYNC: &str = "\x1b[?2026l";
+
+/// The crossterm-backed implementation, writing queued commands to `stderr`.
+pub struct CrosstermBackend {
+    /// The richest color tier to emit; colors are degraded to it before being
+    /// handed to crossterm so truecolor never reaches a 16-color terminal.
+    color_level: ColorLevel,
+}
+
+impl CrosstermBackend {
+    pub fn new(color_level: ColorLevel) -> Self {
+        Self { color_level }
+    }
+}
+
+impl Backend for CrosstermBackend {
+    fn enter_raw_mode(&mut self) -> io::Result<()> {
+        crossterm::terminal::enable_raw_mode().map_err(into_io)
+    }
+
+    fn leave_raw_mode(&mut self) -> io::Result<()> {
+        crossterm::terminal::disable_raw_mode().map_err(into_io)
+    }
+
+    fn window_size(&mut self) -> io::Result<(u16, u16)> {
+        crossterm::terminal::size().map_err(into_io)
+    }
+
+    fn cursor_row(&mut self) -> io::Result<u16> {
+        crossterm::cursor::position().map(|(_, row)| row).map_err(into_io)
+    }
+
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<bool> {
+        crossterm::event::poll(timeout).map_err(into_io)
+    }
+
+    fn read_event(&mut self) -> io::Result<Event> {
+        loop {
+            match crossterm::event::read().map_err(into_io)? {
+                crossterm::event::Event::Key(evt) => return Ok(Event::Key(from_crossterm_key(evt))),
+                crossterm::event::Event::Resize(w, h) => return Ok(Event::Resize(w, h)),
+                crossterm::event::Event::Mouse(_) => continue,
+            }
+        }
+    }
+
+    fn move_to_column(&mut self, col: u16) -> io::Result<()> {
+        queue_stderr(crossterm::cursor::MoveToColumn(col))
+    }
+
+    fn move_to_next_line(&mut self, n: u16) -> io::Result<()> {
+        queue_stderr(crossterm::cursor::MoveToNextLine(n))
+    }
+
+    fn move_to_previous_line(&mut self, n: u16) -> io::Result<()> {
+        queue_stderr(crossterm::cursor::MoveToPreviousLine(n))
+    }
+
+    fn clear_current_line(&mut self) -> io::Result<()> {
+        queue_stderr(crossterm::terminal::Clear(
+            crossterm::terminal::ClearType::CurrentLine,
+        ))
+    }
+
+    fn save_position(&mut self) -> io::Result<()> {
+        queue_stderr(crossterm::cursor::SavePosition)
+    }
+
+    fn restore_position(&mut self) -> io::Result<()> {
+        queue_stderr(crossterm::cursor::RestorePosition)
+    }
+
+    fn print_styled(&mut self, text: &str, style: &CellStyle) -> io::Result<()> {
+        use std::io::Write;
+        let stderr = io::stderr();
+        let mut stderr = stderr.lock();
+        crossterm::queue!(
+            stderr,
+            crossterm::style::PrintStyledContent(to_content_style(style, self.color_level).apply(text))
+        )
+        .map_err(into_io)?;
+        let _ = stderr.flush();
+        Ok(())
+    }
+
+    fn begin_sync(&mut self) -> io::Result<()> {
+        queue_stderr(crossterm::style::Print(BEGIN_SYNC))
+    }
+
+    fn end_sync(&mut self) -> io::Result<()> {
+        queue_stderr(crossterm::style::Print(END_SYNC))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        use std::io::Write;
+        io::stderr().flush()
+    }
+}
+
+/// The native `Console`-backed implementation. Raw mode lives and dies with the
+/// wrapped `Console`, so there is no duplicated termios bookkeeping here.
+#[cfg(not(windows))]
+pub struct ConsoleBackend {
+    console: Option<Console>,
+    /// The color tier forced on the wrapped `Console` once it enters raw mode.
+    color_level: ColorLevel,
+}
+
+#[cfg(not(windows))]
+impl ConsoleBackend {
+    pub fn new(color_level: ColorLevel) -> Self {
+        Self {
+            console: None,
+            color_level,
+        }
+    }
+
+    fn console(&mut self) -> io::Result<&mut Console> {
+        self.console
+            .as_mut()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "console not in raw mode"))
+    }
+}
+
+#[cfg(not(windows))]
+impl Backend for ConsoleBackend {
+    fn enter_raw_mode(&mut self) -> io::Result<()> {
+        let mut console = Console::new()?;
+        console.set_color_level(self.color_level);
+        self.console = Some(console);
+        Ok(())
+    }
+
+    fn leave_raw_mode(&mut self) -> io::Result<()> {
+        // Dropping the console restores the saved termios.
+        self.console = None;
+        Ok(())
+    }
+
+    fn window_size(&mut self) -> io::Result<(u16, u16)> {
+        self.console()?.window_size()
+    }
+
+    fn cursor_row(&mut self) -> io::Result<u16> {
+        // The native backend can't cheaply query the cursor row; assume the
+        // prompt starts at the top of the usable area.
+        Ok(0)
+    }
+
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<bool> {
+        self.console()?.poll_input(timeout)
+    }
+
+    fn read_event(&mut self) -> io::Result<Event> {
+        self.console()?.read_key().map(Event::Key)
+    }
+
+    fn move_to_column(&mut self, col: u16) -> io::Result<()> {
+        self.console()?.move_to_column(col)
+    }
+
+    fn move_to_next_line(&mut self, n: u16) -> io::Result<()> {
+        self.console()?.move_down_n(n as usize)
+    }
+
+    fn move_to_previous_line(&mut self, n: u16) -> io::Result<()> {
+        self.console()?.move_up_n(n as usize)
+    }
+
+    fn clear_current_line(&mut self) -> io::Result<()> {
+        self.console()?.clear_line()
+    }
+
+    fn save_position(&mut self) -> io::Result<()> {
+        self.console()?.save_caret_position()
+    }
+
+    fn restore_position(&mut self) -> io::Result<()> {
+        self.console()?.restore_caret_position()
+    }
+
+    fn print_styled(&mut self, text: &str, style: &CellStyle) -> io::Result<()> {
+        use std::io::Write;
+        let console = self.console()?;
+        console.apply_style(&to_console_style(style))?;
+        // Render to stderr alongside the rest of the console output; stdout is
+        // reserved for the final selection.
+        write!(io::stderr(), "{}", text)?;
+        console.reset_all()
+    }
+
+    fn begin_sync(&mut self) -> io::Result<()> {
+        self.console()?.begin_synchronized_update()
+    }
+
+    fn end_sync(&mut self) -> io::Result<()> {
+        self.console()?.end_synchronized_update()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.console()?.flush()
+    }
+}
+
+/// Translate a `CellStyle` into the native console `Style` compound.
+#[cfg(not(windows))]
+fn to_console_style(style: &CellStyle) -> Style {
+    let mut parts = Vec::new();
+    if let Some(color) = style.foreground {
+        parts.push(Style::Foreground(color));
+    }
+    if let Some(color) = style.background {
+        parts.push(Style::Background(color));
+    }
+    if style.bold {
+        parts.push(Style::Bold);
+    }
+    if style.underline {
+        parts.push(Style::Underlined);
+    }
+    Style::Compound(parts)
+}
+
+/// Translate a `CellStyle` into a crossterm `ContentStyle`, degrading each
+/// color to `level` first so a truecolor value is never emitted to a terminal
+/// that can't render it.
+fn to_content_style(style: &CellStyle, level: ColorLevel) -> crossterm::style::ContentStyle {
+    let mut content = crossterm::style::ContentStyle::new();
+    content.foreground_color = style.foreground.map(|c| to_crossterm_color(c.degrade(level)));
+    content.background_color = style.background.map(|c| to_crossterm_color(c.degrade(level)));
+    if style.bold {
+        content.attributes.set(crossterm::style::Attribute::Bold);
+    }
+    if style.underline {
+        content
+            .attributes
+            .set(crossterm::style::Attribute::Underlined);
+    }
+    content
+}
+
+/// Map a native `Color` onto the closest crossterm `Color`.
+fn to_crossterm_color(color: TermColor) -> crossterm::style::Color {
+    use crossterm::style::Color as C;
+    match color {
+        TermColor::Standard(n) => C::AnsiValue(n),
+        TermColor::Bold(n) => C::AnsiValue(n + 8),
+        TermColor::Cube(r, g, b) => C::AnsiValue(16 + 36 * r + 6 * g + b),
+        TermColor::Grayscale(n) => C::AnsiValue(232 + n),
+        TermColor::True(r, g, b) => C::Rgb { r, g, b },
+    }
+}
+
+/// Fold a crossterm key event into the native `Key` enum.
+fn from_crossterm_key(evt: crossterm::event::KeyEvent) -> Key {
+    use crossterm::event::{KeyCode, KeyModifiers};
+    match evt.code {
+        KeyCode::Char('c') if evt.modifiers.contains(KeyModifiers::CONTROL) => Key::CtrlC,
+        KeyCode::Char(c) if evt.modifiers.contains(KeyModifiers::CONTROL) => {
+            Key::Ctrl(c.to_ascii_lowercase())
+        }
+        KeyCode::Char(c) => Key::Char(c),
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Esc => Key::Esc,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Up => Key::Up,
+        KeyCode::Down => Key::Down,
+        KeyCode::Left => Key::Left,
+        KeyCode::Right => Key::Right,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        _ => Key::Unknown(0),
+    }
+}
+
+fn into_io(err: crossterm::ErrorKind) -> io::Error {
+    match err {
+        crossterm::ErrorKind::IoError(e) => e,
+        other => io::Error::new(io::ErrorKind::Other, other.to_string()),
+    }
+}
+
+fn queue_stderr(command: impl crossterm::Command) -> io::Result<()> {
+    use std::io::Write;
+    let stderr = io::stderr();
+    let mut stderr = stderr.lock();
+    crossterm::queue!(stderr, command).map_err(into_io)?;
+    let _ = stderr.flush();
+    Ok(())
+}
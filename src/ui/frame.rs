@@ -0,0 +1,83 @@
+use std::io;
+
+use super::backend::{Backend, CellStyle};
+
+/// A run of text sharing a single style within a row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: CellStyle,
+}
+
+impl Span {
+    pub fn new(text: impl Into<String>, style: CellStyle) -> Self {
+        Self {
+            text: text.into(),
+            style,
+        }
+    }
+}
+
+/// The desired state of the screen as a stack of styled rows.
+///
+/// Row 0 is the prompt line (the one the cursor sits on); every subsequent row
+/// is a candidate below it. Rendering builds a fresh `Frame` each tick and
+/// [`reconcile`](Frame::reconcile)s it against the previously displayed one,
+/// touching only the rows that actually changed instead of clearing and
+/// rewriting the whole list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    rows: Vec<Vec<Span>>,
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a row built from its spans.
+    pub fn push_row(&mut self, spans: Vec<Span>) {
+        self.rows.push(spans);
+    }
+
+    /// Draw this frame, emitting writes only for rows that differ from `prev`.
+    ///
+    /// The caret is assumed to sit at the start of row 0's content; it is saved
+    /// up front and restored at the end, so callers can reposition it
+    /// afterwards (e.g. onto the query caret).
+    pub fn reconcile<B: Backend>(&self, prev: &Frame, backend: &mut B) -> io::Result<()> {
+        backend.save_position()?;
+
+        for row in 0..self.rows.len() {
+            if prev.rows.get(row) == Some(&self.rows[row]) {
+                continue;
+            }
+
+            // Move onto the target row, clear it, then write its spans.
+            self.seek_row(row, backend)?;
+            for span in &self.rows[row] {
+                backend.print_styled(&span.text, &span.style)?;
+            }
+        }
+
+        // Any rows the previous frame had but this one doesn't must be erased.
+        for row in self.rows.len()..prev.rows.len() {
+            self.seek_row(row, backend)?;
+        }
+
+        backend.restore_position()?;
+        Ok(())
+    }
+
+    /// Park the caret at the start of `row`, relative to the saved position, and
+    /// clear whatever is there.
+    fn seek_row<B: Backend>(&self, row: usize, backend: &mut B) -> io::Result<()> {
+        backend.restore_position()?;
+        if row > 0 {
+            backend.move_to_next_line(row as u16)?;
+        } else {
+            backend.move_to_column(0)?;
+        }
+        backend.clear_current_line()
+    }
+}
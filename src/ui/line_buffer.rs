@@ -0,0 +1,93 @@
+//! An editable query line with a cursor.
+//!
+//! The query used to be a bare `String` that only ever grew and shrank at the
+//! end; this tracks a cursor byte-offset as well, so the usual Emacs/readline
+//! editing commands (move by character, jump to either end, delete a word, kill
+//! to the start) can act in the middle of the line the way `rustyline` exposes
+//! them. Movement lands on `char` boundaries.
+
+/// A line of text plus a cursor position into it, measured as a byte offset.
+#[derive(Debug, Clone, Default)]
+pub struct LineBuffer {
+    buf: String,
+    cursor: usize,
+}
+
+impl LineBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current contents.
+    pub fn as_str(&self) -> &str {
+        &self.buf
+    }
+
+    /// The cursor's column, counted in characters from the start of the line.
+    pub fn caret(&self) -> usize {
+        self.buf[..self.cursor].chars().count()
+    }
+
+    /// Insert a character at the cursor and step past it.
+    pub fn insert(&mut self, ch: char) {
+        self.buf.insert(self.cursor, ch);
+        self.cursor += ch.len_utf8();
+    }
+
+    /// Delete the character before the cursor, if any.
+    pub fn backspace(&mut self) {
+        if let Some(ch) = self.buf[..self.cursor].chars().next_back() {
+            self.cursor -= ch.len_utf8();
+            self.buf.remove(self.cursor);
+        }
+    }
+
+    /// Delete the character under the cursor, if any.
+    pub fn delete(&mut self) {
+        if self.cursor < self.buf.len() {
+            self.buf.remove(self.cursor);
+        }
+    }
+
+    /// Move one character left.
+    pub fn move_left(&mut self) {
+        if let Some(ch) = self.buf[..self.cursor].chars().next_back() {
+            self.cursor -= ch.len_utf8();
+        }
+    }
+
+    /// Move one character right.
+    pub fn move_right(&mut self) {
+        if let Some(ch) = self.buf[self.cursor..].chars().next() {
+            self.cursor += ch.len_utf8();
+        }
+    }
+
+    /// Jump to the start of the line.
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    /// Jump to the end of the line.
+    pub fn move_end(&mut self) {
+        self.cursor = self.buf.len();
+    }
+
+    /// Delete the word before the cursor: the run of whitespace immediately to
+    /// the left, then the run of non-whitespace before that.
+    pub fn delete_prev_word(&mut self) {
+        let left = &self.buf[..self.cursor];
+        let trimmed = left
+            .trim_end_matches(char::is_whitespace)
+            .trim_end_matches(|c: char| !c.is_whitespace());
+        let start = trimmed.len();
+        self.buf.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Delete everything from the start of the line up to the cursor.
+    pub fn kill_to_start(&mut self) {
+        self.buf.replace_range(..self.cursor, "");
+        self.cursor = 0;
+    }
+}
@@ -0,0 +1,111 @@
+//! Display-width- and ANSI-aware helpers for laying out a rendered row.
+//!
+//! Match text is arbitrary Unicode and may already carry embedded SGR escape
+//! sequences (pre-colored input piped in from another tool). Measuring such a
+//! string by its byte or `char` count overshoots — CJK and emoji occupy two
+//! columns, combining marks none, and escape sequences none at all — so rows
+//! built from raw lengths wrap past the terminal edge and wreck the layout.
+//! These helpers measure the *visible* width instead and truncate a row of
+//! spans to the terminal column count, keeping any highlight spans that remain
+//! visible styled correctly.
+
+use unicode_width::UnicodeWidthChar;
+
+use super::frame::Span;
+
+/// The ellipsis appended to a row that had to be cut short; one column wide.
+const ELLIPSIS: &str = "…";
+
+/// The display width of `s` in terminal columns, counting embedded SGR escape
+/// sequences as zero-width so pre-colored input lines up with plain input.
+pub fn display_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            consume_escape(&mut chars);
+            continue;
+        }
+        width += ch.width().unwrap_or(0);
+    }
+    width
+}
+
+/// Truncate `spans` so their combined visible width fits in `columns`, leaving
+/// room for a trailing ellipsis when anything is dropped. Styles are preserved
+/// across the cut, and an escape sequence straddling the boundary is kept whole
+/// so a row never ends mid-sequence.
+pub fn truncate_spans(spans: Vec<Span>, columns: usize) -> Vec<Span> {
+    if columns == 0 {
+        return Vec::new();
+    }
+
+    let total: usize = spans.iter().map(|span| display_width(&span.text)).sum();
+    if total <= columns {
+        return spans;
+    }
+
+    // Reserve the final column for the ellipsis marking the truncation.
+    let budget = columns - 1;
+    let mut used = 0;
+    let mut out = Vec::new();
+
+    for span in spans {
+        if used >= budget {
+            break;
+        }
+
+        let mut kept = String::new();
+        let mut chars = span.text.chars().peekable();
+        while let Some(ch) = chars.next() {
+            if ch == '\x1b' {
+                // Escapes are zero-width; carry the whole sequence through.
+                kept.push(ch);
+                push_escape(&mut chars, &mut kept);
+                continue;
+            }
+
+            let w = ch.width().unwrap_or(0);
+            if used + w > budget {
+                chars = "".chars().peekable();
+                break;
+            }
+            used += w;
+            kept.push(ch);
+        }
+
+        if !kept.is_empty() {
+            out.push(Span::new(kept, span.style));
+        }
+    }
+
+    out.push(Span::new(ELLIPSIS, Default::default()));
+    out
+}
+
+/// Skip the rest of an escape sequence after the leading `\x1b` has been read:
+/// a CSI `[` introducer if present, then up to and including the final byte
+/// (the first in the `0x40..=0x7e` range).
+fn consume_escape<I: Iterator<Item = char>>(chars: &mut std::iter::Peekable<I>) {
+    // `[` itself lies in the final-byte range, so step over it first.
+    let _ = chars.next_if_eq(&'[');
+    for ch in chars.by_ref() {
+        if matches!(ch, '@'..='~') {
+            break;
+        }
+    }
+}
+
+/// Like [`consume_escape`], but append the skipped bytes to `out` instead of
+/// discarding them.
+fn push_escape<I: Iterator<Item = char>>(chars: &mut std::iter::Peekable<I>, out: &mut String) {
+    if let Some(ch) = chars.next_if_eq(&'[') {
+        out.push(ch);
+    }
+    for ch in chars.by_ref() {
+        out.push(ch);
+        if matches!(ch, '@'..='~') {
+            break;
+        }
+    }
+}